@@ -0,0 +1,444 @@
+//! Serializes a `CompilationUnit` back into `.class` bytes, inverting
+//! `disassembler::transform`. Mirrors Krakatau's paired assembler/
+//! disassembler design. The output carries the class header, a rebuilt
+//! constant pool, fields (with `ConstantValue`) and method headers; method
+//! `Code` attributes and class-level attributes are not emitted, so
+//! `assemble(transform(class_file))` does not reproduce method bodies.
+
+use disassembler::class::*;
+use disassembler::transform::{signature_to_descriptor, type_to_descriptor};
+use std::collections::HashMap;
+
+/// Interns constant-pool entries and renumbers them on the fly, following
+/// the JVM's one-based indexing and the rule that `Long`/`Double` entries
+/// occupy two consecutive indices (the second is left unused).
+struct ConstantPoolBuilder {
+    entries: Vec<ConstantInfo>,
+    // Number of one-index gaps burned so far by `Long`/`Double` entries,
+    // tracked separately from `entries.len()` since the gap itself is never
+    // pushed as an entry.
+    wide_gaps: u16,
+    utf8: HashMap<String, u16>,
+    classes: HashMap<String, u16>,
+    name_and_types: HashMap<(u16, u16), u16>,
+    field_refs: HashMap<(u16, u16), u16>,
+    method_refs: HashMap<(u16, u16), u16>,
+}
+
+impl ConstantPoolBuilder {
+    fn new() -> Self {
+        ConstantPoolBuilder {
+            entries: vec![],
+            wide_gaps: 0,
+            utf8: HashMap::new(),
+            classes: HashMap::new(),
+            name_and_types: HashMap::new(),
+            field_refs: HashMap::new(),
+            method_refs: HashMap::new(),
+        }
+    }
+
+    /// Index of the next entry to be pushed (constant-pool indices are
+    /// one-based; `entries.len()` plus the indices burned by earlier
+    /// `Long`/`Double` gaps gives the count of indices handed out so far).
+    fn next_index(&self) -> u16 {
+        self.entries.len() as u16 + self.wide_gaps + 1
+    }
+
+    fn push(&mut self, constant: ConstantInfo) -> u16 {
+        let index = self.next_index();
+        self.entries.push(constant);
+        index
+    }
+
+    /// `Long`/`Double` entries burn the following index too, but — unlike
+    /// `Utf8`/`Class`/etc. — that second index has no entry of its own in
+    /// the JVM spec's constant pool and must never be written out.
+    fn push_wide(&mut self, constant: ConstantInfo) -> u16 {
+        let index = self.push(constant);
+        self.wide_gaps += 1;
+        index
+    }
+
+    fn intern_utf8(&mut self, s: &str) -> u16 {
+        if let Some(&index) = self.utf8.get(s) {
+            return index;
+        }
+        let index = self.push(ConstantInfo::Utf8(s.to_owned()));
+        self.utf8.insert(s.to_owned(), index);
+        index
+    }
+
+    fn intern_class(&mut self, name: &str) -> u16 {
+        if let Some(&index) = self.classes.get(name) {
+            return index;
+        }
+        let name_index = self.intern_utf8(name);
+        let index = self.push(ConstantInfo::Class { name_index: name_index });
+        self.classes.insert(name.to_owned(), index);
+        index
+    }
+
+    fn intern_name_and_type(&mut self, name: &str, descriptor: &str) -> u16 {
+        let name_index = self.intern_utf8(name);
+        let descriptor_index = self.intern_utf8(descriptor);
+        let key = (name_index, descriptor_index);
+        if let Some(&index) = self.name_and_types.get(&key) {
+            return index;
+        }
+        let index = self.push(ConstantInfo::NameAndType {
+            name_index: name_index,
+            descriptor_index: descriptor_index,
+        });
+        self.name_and_types.insert(key, index);
+        index
+    }
+
+    fn intern_field_ref(&mut self, field: &FieldRef, class_refs: &HashMap<u16, ClassRef>) -> u16 {
+        let class_index = self.intern_class(&class_refs[&field.class_ref].0);
+        let name_and_type_index =
+            self.intern_name_and_type(&field.name, &type_to_descriptor(&field.typ, &[]));
+        let key = (class_index, name_and_type_index);
+        if let Some(&index) = self.field_refs.get(&key) {
+            return index;
+        }
+        let index = self.push(ConstantInfo::FieldRef {
+            class_index: class_index,
+            name_index: name_and_type_index,
+        });
+        self.field_refs.insert(key, index);
+        index
+    }
+
+    fn intern_method_ref(&mut self, method: &MethodRef, class_refs: &HashMap<u16, ClassRef>) -> u16 {
+        let class_index = self.intern_class(&class_refs[&method.class_ref].0);
+        let name_and_type_index =
+            self.intern_name_and_type(&method.name, &signature_to_descriptor(&method.signature));
+        let key = (class_index, name_and_type_index);
+        if let Some(&index) = self.method_refs.get(&key) {
+            return index;
+        }
+        let index = self.push(ConstantInfo::MethodRef {
+            class_index: class_index,
+            name_index: name_and_type_index,
+        });
+        self.method_refs.insert(key, index);
+        index
+    }
+
+    fn intern_java_constant(&mut self, constant: &JavaConstant) -> u16 {
+        match *constant {
+            JavaConstant::Integer(value) => self.push(ConstantInfo::Integer(value)),
+            JavaConstant::Long(value) => self.push_wide(ConstantInfo::Long(value)),
+            JavaConstant::Float(value) => self.push(ConstantInfo::Float(value)),
+            JavaConstant::Double(value) => self.push_wide(ConstantInfo::Double(value)),
+            JavaConstant::String(ref value) => {
+                let string_index = self.intern_utf8(value);
+                self.push(ConstantInfo::String { string_index: string_index })
+            }
+        }
+    }
+
+    fn into_pool(self) -> ConstantPool {
+        ConstantPool { constants: self.entries }
+    }
+}
+
+/// A field or method's `name_index`/`descriptor_index`/`attributes`, already
+/// resolved against the constant pool being built. Class files store fields
+/// and methods with an identical header shape, so both reuse this.
+struct MemberEntry {
+    access_flags: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    /// `(attribute_name_index, attribute_value_index)` — every attribute we
+    /// can currently re-emit (just `ConstantValue`) is a single `u2` value,
+    /// so there's no need for a general-purpose attribute payload yet.
+    attributes: Vec<(u16, u16)>,
+}
+
+/// Serializes `unit` into the bytes of a `.class` file.
+///
+/// A method's `Code` attribute is not re-assembled: every method and
+/// constructor is written with no attributes, so its body is dropped.
+pub fn assemble(unit: &CompilationUnit) -> Vec<u8> {
+    let mut pool = ConstantPoolBuilder::new();
+
+    let this_class_index = pool.intern_class(&unit.this_class.0);
+    let super_class_index = match unit.super_class {
+        Some(ref class_ref) => pool.intern_class(&class_ref.0),
+        None => 0,
+    };
+    let interface_indices: Vec<u16> = unit.interfaces
+        .iter()
+        .map(|class_ref| pool.intern_class(&class_ref.0))
+        .collect();
+
+    let mut constant_value_name_index = None;
+    let mut fields = vec![];
+    let mut methods = vec![];
+    for declaration in &unit.declarations {
+        match *declaration {
+            Declaration::Field { ref modifiers, ref name, ref typ, ref constant_value } => {
+                let attributes = match *constant_value {
+                    Some(ref constant) => {
+                        let name_index = *constant_value_name_index
+                            .get_or_insert_with(|| pool.intern_utf8("ConstantValue"));
+                        vec![(name_index, pool.intern_java_constant(constant))]
+                    }
+                    None => vec![],
+                };
+                fields.push(MemberEntry {
+                    access_flags: flags_from_modifiers(modifiers).bits(),
+                    name_index: pool.intern_utf8(name),
+                    descriptor_index: pool.intern_utf8(&type_to_descriptor(typ, &[])),
+                    attributes: attributes,
+                });
+            }
+            Declaration::Method { ref modifiers, ref name, ref signature, .. } => {
+                methods.push(MemberEntry {
+                    access_flags: flags_from_modifiers(modifiers).bits(),
+                    name_index: pool.intern_utf8(name),
+                    descriptor_index: pool.intern_utf8(&signature_to_descriptor(signature)),
+                    attributes: vec![],
+                });
+            }
+            Declaration::Constructor { ref modifiers, ref signature, .. } => {
+                methods.push(MemberEntry {
+                    access_flags: flags_from_modifiers(modifiers).bits(),
+                    name_index: pool.intern_utf8("<init>"),
+                    descriptor_index: pool.intern_utf8(&signature_to_descriptor(signature)),
+                    attributes: vec![],
+                });
+            }
+            Declaration::StaticInitializer { .. } => {
+                methods.push(MemberEntry {
+                    access_flags: ACC_STATIC.bits(),
+                    name_index: pool.intern_utf8("<clinit>"),
+                    descriptor_index: pool.intern_utf8("()V"),
+                    attributes: vec![],
+                });
+            }
+        }
+    }
+    for field_ref in unit.field_refs.values() {
+        pool.intern_field_ref(field_ref, &unit.class_refs);
+    }
+    for method_ref in unit.method_refs.values() {
+        pool.intern_method_ref(method_ref, &unit.class_refs);
+    }
+    for constant in unit.java_constants.values() {
+        pool.intern_java_constant(constant);
+    }
+    for string in unit.string_constants.values() {
+        pool.intern_utf8(string);
+    }
+
+    let constant_pool = pool.into_pool();
+
+    let mut bytes = vec![];
+    // Magic number, then minor/major version (Java 8: 0/52).
+    bytes.extend_from_slice(&[0xCA, 0xFE, 0xBA, 0xBE]);
+    push_u16(&mut bytes, 0);
+    push_u16(&mut bytes, 52);
+    write_constant_pool(&mut bytes, &constant_pool);
+    push_u16(&mut bytes, class_flags_from_modifiers(unit));
+    push_u16(&mut bytes, this_class_index);
+    push_u16(&mut bytes, super_class_index);
+    push_u16(&mut bytes, interface_indices.len() as u16);
+    for index in &interface_indices {
+        push_u16(&mut bytes, *index);
+    }
+    write_members(&mut bytes, &fields);
+    write_members(&mut bytes, &methods);
+    // No class-level attributes (e.g. `SourceFile`, `BootstrapMethods`) are
+    // emitted: `attributes_count` is always 0.
+    push_u16(&mut bytes, 0);
+    bytes
+}
+
+fn write_members(bytes: &mut Vec<u8>, members: &[MemberEntry]) {
+    push_u16(bytes, members.len() as u16);
+    for member in members {
+        push_u16(bytes, member.access_flags);
+        push_u16(bytes, member.name_index);
+        push_u16(bytes, member.descriptor_index);
+        push_u16(bytes, member.attributes.len() as u16);
+        for &(name_index, value_index) in &member.attributes {
+            push_u16(bytes, name_index);
+            push_u32(bytes, 2); // attribute_length: a single `u2` value.
+            push_u16(bytes, value_index);
+        }
+    }
+}
+
+/// Maps every `Modifier`, including the field- and method-only ones, onto its
+/// `AccessFlags` bit. Class-kind flags (`ACC_INTERFACE`, `ACC_ENUM`) have no
+/// `Modifier` and are layered on by the caller.
+fn flags_from_modifiers(modifiers: &[Modifier]) -> AccessFlags {
+    let mut flags = AccessFlags::empty();
+    for modifier in modifiers {
+        flags |= match *modifier {
+            Modifier::Public => ACC_PUBLIC,
+            Modifier::Protected => ACC_PROTECTED,
+            Modifier::Private => ACC_PRIVATE,
+            Modifier::Static => ACC_STATIC,
+            Modifier::Final => ACC_FINAL,
+            Modifier::Abstract => ACC_ABSTRACT,
+            Modifier::Synchronized => ACC_SYNCHRONIZED,
+            Modifier::Native => ACC_NATIVE,
+            Modifier::Strictfp => ACC_STRICT,
+            Modifier::Volatile => ACC_VOLATILE,
+            Modifier::Transient => ACC_TRANSIENT,
+        };
+    }
+    flags
+}
+
+fn class_flags_from_modifiers(unit: &CompilationUnit) -> u16 {
+    let mut flags = flags_from_modifiers(&unit.modifiers);
+    flags |= match unit.typ {
+        UnitType::Interface => ACC_INTERFACE,
+        UnitType::Enum => ACC_ENUM,
+        UnitType::Class => AccessFlags::empty(),
+    };
+    flags.bits()
+}
+
+fn write_constant_pool(bytes: &mut Vec<u8>, pool: &ConstantPool) {
+    push_u16(bytes, constant_pool_count(pool));
+    for constant in &pool.constants {
+        write_constant(bytes, constant);
+    }
+}
+
+/// `constant_pool_count` is one more than the highest valid index, which
+/// isn't `pool.constants.len()` when `Long`/`Double` entries are present:
+/// each of those burns an extra index that is never physically emitted.
+fn constant_pool_count(pool: &ConstantPool) -> u16 {
+    1 +
+        pool.constants
+            .iter()
+            .map(|constant| match *constant {
+                ConstantInfo::Long(_) | ConstantInfo::Double(_) => 2,
+                _ => 1,
+            })
+            .sum::<u16>()
+}
+
+fn write_constant(bytes: &mut Vec<u8>, constant: &ConstantInfo) {
+    match *constant {
+        ConstantInfo::Utf8(ref s) => {
+            bytes.push(1);
+            push_u16(bytes, s.len() as u16);
+            bytes.extend_from_slice(s.as_bytes());
+        }
+        ConstantInfo::Integer(value) => {
+            bytes.push(3);
+            bytes.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        ConstantInfo::Float(value) => {
+            bytes.push(4);
+            bytes.extend_from_slice(&value.to_bits().to_be_bytes());
+        }
+        ConstantInfo::Long(value) => {
+            bytes.push(5);
+            bytes.extend_from_slice(&(value as u64).to_be_bytes());
+        }
+        ConstantInfo::Double(value) => {
+            bytes.push(6);
+            bytes.extend_from_slice(&value.to_bits().to_be_bytes());
+        }
+        ConstantInfo::Class { name_index } => {
+            bytes.push(7);
+            push_u16(bytes, name_index);
+        }
+        ConstantInfo::String { string_index } => {
+            bytes.push(8);
+            push_u16(bytes, string_index);
+        }
+        ConstantInfo::FieldRef { class_index, name_index } => {
+            bytes.push(9);
+            push_u16(bytes, class_index);
+            push_u16(bytes, name_index);
+        }
+        ConstantInfo::MethodRef { class_index, name_index } => {
+            bytes.push(10);
+            push_u16(bytes, class_index);
+            push_u16(bytes, name_index);
+        }
+        ConstantInfo::InterfaceMethodref { class_index, name_index } => {
+            bytes.push(11);
+            push_u16(bytes, class_index);
+            push_u16(bytes, name_index);
+        }
+        ConstantInfo::NameAndType { name_index, descriptor_index } => {
+            bytes.push(12);
+            push_u16(bytes, name_index);
+            push_u16(bytes, descriptor_index);
+        }
+        ConstantInfo::MethodHandle { reference_kind, reference_index } => {
+            bytes.push(15);
+            bytes.push(reference_kind);
+            push_u16(bytes, reference_index);
+        }
+        ConstantInfo::MethodType { descriptor_index } => {
+            bytes.push(16);
+            push_u16(bytes, descriptor_index);
+        }
+        ConstantInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index } => {
+            bytes.push(17);
+            push_u16(bytes, bootstrap_method_attr_index);
+            push_u16(bytes, name_and_type_index);
+        }
+        ConstantInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+            bytes.push(18);
+            push_u16(bytes, bootstrap_method_attr_index);
+            push_u16(bytes, name_and_type_index);
+        }
+    }
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_be_bytes());
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // This doesn't round-trip a whole class file; it pins the part that's
+    // easiest to get subtly wrong: a `Long`/`Double` entry must burn two
+    // indices without a second entry ever being written to the byte stream.
+    #[test]
+    fn wide_constants_burn_an_index_without_emitting_a_second_entry() {
+        let mut pool = ConstantPoolBuilder::new();
+        let first = pool.intern_utf8("a");
+        let long_index = pool.push_wide(ConstantInfo::Long(1));
+        let after = pool.intern_utf8("b");
+
+        assert_eq!(first, 1);
+        assert_eq!(long_index, 2);
+        assert_eq!(after, 4); // index 3 is the long's unemitted second slot.
+
+        let constant_pool = pool.into_pool();
+        assert_eq!(constant_pool.constants.len(), 3);
+        assert_eq!(constant_pool_count(&constant_pool), 5);
+
+        let mut bytes = vec![];
+        write_constant_pool(&mut bytes, &constant_pool);
+        assert_eq!(&bytes[0..2], &[0, 5]); // constant_pool_count
+    }
+
+    #[test]
+    fn interning_the_same_utf8_twice_reuses_the_index() {
+        let mut pool = ConstantPoolBuilder::new();
+        let first = pool.intern_utf8("java/lang/Object");
+        let second = pool.intern_utf8("java/lang/Object");
+        assert_eq!(first, second);
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use decompiler::cfg::*;
 use decompiler::types::*;
 use disassembler::instructions::*;
@@ -24,35 +26,215 @@ pub fn convert_bin_op(op: BinaryOp) -> BinOp {
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
-pub struct StackLayout(pub StackVarId);
+/// The JVM distinguishes *category-1* types (everything except `long`/`double`),
+/// which occupy a single operand-stack word, from *category-2* types, which
+/// occupy two. `StackLayout` needs to know this to compute correct slot
+/// offsets for `Invoke`, `dup2`/`pop2`, and friends.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Category {
+    One,
+    Two,
+}
+
+impl Category {
+    pub fn of(typ: &Type) -> Self {
+        match *typ {
+            Type::Long | Type::Double => Category::Two,
+            _ => Category::One,
+        }
+    }
+
+    pub fn words(&self) -> isize {
+        match *self {
+            Category::One => 1,
+            Category::Two => 2,
+        }
+    }
+}
+
+/// A single live entry on the operand stack, together with the number of
+/// JVM "words" it occupies.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StackSlot {
+    pub id: StackVarId,
+    pub category: Category,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StackLayout {
+    slots: Vec<StackSlot>,
+    next_id: StackVarId,
+    /// Category most recently stored into each local slot, learned from
+    /// `Store` as it's executed so a later `Load` of the same local (e.g. a
+    /// `long`/`double` re-read after `lstore`/`dstore`) reports the right
+    /// width instead of defaulting to `Category::One`.
+    local_categories: HashMap<usize, Category>,
+}
 
 impl StackLayout {
     pub fn new() -> Self {
-        StackLayout(0)
+        StackLayout {
+            slots: vec![],
+            next_id: 0,
+            local_categories: HashMap::new(),
+        }
     }
 
+    /// Number of JVM words currently on the stack.
+    pub fn word_count(&self) -> isize {
+        self.slots.iter().map(|slot| slot.category.words()).sum()
+    }
+
+    /// The word layout of the stack (category per slot, bottom to top),
+    /// ignoring the concrete `stack_<id>` names. Two predecessors of a CFG
+    /// join reach it having minted a different number of fresh ids along the
+    /// way even when the operand stack shape they arrive with is identical,
+    /// so this -- not slot-for-slot equality -- is what a join must agree on.
+    pub fn categories(&self) -> Vec<Category> {
+        self.slots.iter().map(|slot| slot.category).collect()
+    }
+
+    /// Statements that copy `self`'s slots into `canonical`'s ids, one
+    /// `stack_<canonical> = stack_<self>` assignment per slot. Used to bring
+    /// a join's other predecessors in line with whichever predecessor's
+    /// layout was first recorded for the join block, since code inside (and
+    /// after) that block is generated against one fixed set of names.
+    pub fn bridge_to(&self, canonical: &StackLayout) -> Vec<Statement> {
+        self.slots
+            .iter()
+            .zip(canonical.slots.iter())
+            .filter(|&(from, to)| from.id != to.id)
+            .map(|(from, to)| {
+                stmt_expr(Expr::Assign {
+                    to: Box::new(Assignable::Variable(stack(to.id), 0)),
+                    op: None,
+                    from: Box::new(mk_variable(stack(from.id))),
+                })
+            })
+            .collect()
+    }
+
+    /// Look up the variable `i` words below the current top, using the
+    /// disassembler's 1-based convention inherited from the original
+    /// `StackLayout(pub StackVarId)` (`i == 1` is the top-most word, `i == 2`
+    /// the one below it, and so on) -- callers like `LValue::Stack` and
+    /// `LValue::InstanceField::object_stack_index` were written against that
+    /// convention and still pass 1-based indices.
     pub fn get(&self, i: isize) -> StackVarId {
-        self.0 - i
+        let target = self.word_count() - i;
+        let mut word = 0;
+        for slot in &self.slots {
+            if word + slot.category.words() - 1 >= target {
+                return slot.id;
+            }
+            word += slot.category.words();
+        }
+        panic!("stack underflow: no slot at word offset {}", i);
     }
 
     pub fn push(&mut self) -> StackVarId {
-        self.0 += 1;
-        self.0 - 1
+        self.push_cat(Category::One)
+    }
+
+    pub fn push_cat(&mut self, category: Category) -> StackVarId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.slots.push(StackSlot { id: id, category: category });
+        id
     }
 
     pub fn pop(&mut self) -> StackVarId {
-        self.0 -= 1;
-        assert!(self.0 >= 0);
-        self.0
+        let slot = self.slots.pop().expect("stack underflow");
+        slot.id
+    }
+
+    /// Pop and return the slot along with its category, so callers can tell
+    /// category-2 pops (e.g. for `pop2`/`Return` of a `long`) apart from
+    /// category-1 ones.
+    pub fn pop_slot(&mut self) -> StackSlot {
+        self.slots.pop().expect("stack underflow")
+    }
+
+    fn peek_slot(&self) -> StackSlot {
+        *self.slots.last().expect("stack underflow")
+    }
+
+    fn push_slot(&mut self, slot: StackSlot) {
+        self.slots.push(slot);
+    }
+
+    /// Stack-shuffling instructions never create a new value, so they just
+    /// re-reference existing slot ids rather than minting new ones via
+    /// `push`/`push_cat`.
+    fn stack_manage(&mut self, op: &StackManageOp) {
+        match *op {
+            StackManageOp::Dup => {
+                let top = self.peek_slot();
+                self.push_slot(top);
+            }
+            StackManageOp::DupX1 => {
+                let v1 = self.pop_slot();
+                let v2 = self.pop_slot();
+                self.push_slot(v1);
+                self.push_slot(v2);
+                self.push_slot(v1);
+            }
+            StackManageOp::DupX2 => {
+                let v1 = self.pop_slot();
+                let v2 = self.pop_slot();
+                // form 2: v2 alone is a category-2 value, so there's no v3
+                // to sink underneath (same category-2-collapses-a-slot
+                // special case as `Dup2`'s form 2).
+                if v2.category == Category::Two {
+                    self.push_slot(v1);
+                    self.push_slot(v2);
+                    self.push_slot(v1);
+                } else {
+                    let v3 = self.pop_slot();
+                    self.push_slot(v1);
+                    self.push_slot(v3);
+                    self.push_slot(v2);
+                    self.push_slot(v1);
+                }
+            }
+            StackManageOp::Dup2 => {
+                // form 2: a lone category-2 value is duplicated like `dup`.
+                if self.peek_slot().category == Category::Two {
+                    let top = self.peek_slot();
+                    self.push_slot(top);
+                } else {
+                    let v1 = self.pop_slot();
+                    let v2 = self.pop_slot();
+                    self.push_slot(v2);
+                    self.push_slot(v1);
+                    self.push_slot(v2);
+                    self.push_slot(v1);
+                }
+            }
+            StackManageOp::Swap => {
+                let v1 = self.pop_slot();
+                let v2 = self.pop_slot();
+                self.push_slot(v1);
+                self.push_slot(v2);
+            }
+            StackManageOp::Pop => {
+                self.pop_slot();
+            }
+            StackManageOp::Pop2 => {
+                if self.pop_slot().category == Category::One {
+                    self.pop_slot();
+                }
+            }
+        }
     }
 
     pub fn execute(&mut self, instruction: &Instruction, metadata: &Metadata) -> Vec<Statement> {
         match *instruction {
             Instruction::Nop => vec![],
             Instruction::Load(ref rvalue) => {
+                let category = self.category_of_rvalue(rvalue, metadata);
                 let expr = self.make_stack_vars_rvalue(rvalue, metadata);
-                let top = self.push();
+                let top = self.push_cat(category);
                 vec![stmt_expr(Expr::Assign {
                     to: Box::new(Assignable::Variable(stack(top), 0)),
                     op: None,
@@ -61,21 +243,24 @@ impl StackLayout {
             }
             Instruction::Store(ref to) => {
                 let assignable = self.make_stack_vars_lvalue(to, metadata);
-                let top = self.pop();
+                let slot = self.pop_slot();
+                if let LValue::Local(index) = *to {
+                    self.local_categories.insert(index, slot.category);
+                }
                 vec![stmt_expr(Expr::Assign {
                     to: Box::new(assignable),
                     op: None,
-                    from: Box::new(mk_variable(stack(top))),
+                    from: Box::new(mk_variable(stack(slot.id))),
                 })]
             }
             Instruction::Arithm(ref arithm) => match *arithm {
                 Arithm::UnaryOp(op) => {
-                    let v = self.pop();
-                    let res = self.push();
+                    let v = self.pop_slot();
+                    let res = self.push_cat(v.category);
                     let to = Box::new(Assignable::Variable(stack(res), 0));
                     let from = Box::new(Expr::UnaryOp(
                         convert_un_op(op),
-                        Box::new(mk_variable(stack(v))),
+                        Box::new(mk_variable(stack(v.id))),
                     ));
                     vec![stmt_expr(Expr::Assign {
                         to: to,
@@ -84,14 +269,18 @@ impl StackLayout {
                     })]
                 }
                 Arithm::BinaryOp(op) => {
-                    let w = self.pop();
-                    let v = self.pop();
-                    let res = self.push();
+                    // The left operand's category also governs the result:
+                    // for the shifts (`lshl`/`lshr`/`lushr`) the shift
+                    // amount `w` is always a category-1 `int` even when `v`
+                    // is a category-2 `long`.
+                    let w = self.pop_slot();
+                    let v = self.pop_slot();
+                    let res = self.push_cat(v.category);
                     let to = Box::new(Assignable::Variable(stack(res), 0));
                     let from = Box::new(Expr::BinaryOp(
                         convert_bin_op(op),
-                        Box::new(mk_variable(stack(v))),
-                        Box::new(mk_variable(stack(w))),
+                        Box::new(mk_variable(stack(v.id))),
+                        Box::new(mk_variable(stack(w.id))),
                     ));
                     vec![stmt_expr(Expr::Assign {
                         to: to,
@@ -117,16 +306,36 @@ impl StackLayout {
                     })]
                 }
             },
-            Instruction::TypeConv(_) => unimplemented!(),
-            Instruction::ObjManip(_) => unimplemented!(),
-            Instruction::StackManage(_) => unimplemented!(),
+            Instruction::TypeConv(ref to) => {
+                let from = self.pop_slot();
+                let expr = Expr::Cast(to.clone(), Box::new(mk_variable(stack(from.id))));
+                let result = self.push_cat(Category::of(to));
+                vec![stmt_expr(Expr::Assign {
+                    to: Box::new(Assignable::Variable(stack(result), 0)),
+                    op: None,
+                    from: Box::new(expr),
+                })]
+            }
+            Instruction::ObjManip(ref op) => self.execute_obj_manip(op, metadata),
+            Instruction::StackManage(ref op) => {
+                self.stack_manage(op);
+                vec![]
+            }
             Instruction::Jump(_) => unreachable!(),
+            Instruction::Invoke(Invoke { method_index, kind: InvokeKind::Dynamic }) => {
+                self.execute_invoke_dynamic(method_index, metadata)
+            }
             Instruction::Invoke(Invoke { method_index, kind }) => {
                 let method_ref = &metadata.method_refs[&method_index];
                 let class_ref = &metadata.class_refs[&method_ref.class_ref];
-                let args_count = method_ref.signature.parameters.len() as isize;
-                let args_range = self.0 - args_count..self.0;
-                self.0 -= args_count;
+                // Pop one stack slot per parameter, regardless of how many
+                // words a category-2 (long/double) parameter occupies.
+                let mut arg_vars = Vec::with_capacity(method_ref.signature.parameters.len());
+                for _ in &method_ref.signature.parameters {
+                    let slot = self.pop_slot();
+                    arg_vars.push(mk_variable(stack(slot.id)));
+                }
+                arg_vars.reverse();
                 let this_object = match kind {
                     InvokeKind::Special | InvokeKind::Virtual => {
                         let top = self.pop();
@@ -138,15 +347,12 @@ impl StackLayout {
                     this_object,
                     method_ref.clone(),
                     class_ref.clone(),
-                    args_range
-                        .into_iter()
-                        .map(|i| mk_variable(stack(i)))
-                        .collect::<Vec<_>>(),
+                    arg_vars,
                 );
                 if method_ref.signature.return_type == Type::Void {
                     vec![stmt_expr(method_call)]
                 } else {
-                    let result = self.push();
+                    let result = self.push_cat(Category::of(&method_ref.signature.return_type));
                     vec![stmt_expr(Expr::Assign {
                         from: Box::new(method_call),
                         op: None,
@@ -154,15 +360,200 @@ impl StackLayout {
                     })]
                 }
             }
-            Instruction::Throw => unimplemented!(),
-            Instruction::Return(value) => {
-                let value = value.map(|_| {
-                    let top = self.pop();
-                    mk_variable(stack(top))
+            Instruction::Throw => {
+                let exception = self.pop();
+                vec![Statement::Throw(mk_variable(stack(exception)))]
+            }
+            Instruction::Return(category) => {
+                // `category` is `None` for a `return` with no value, and
+                // `Some(Category::Two)` for `lreturn`/`dreturn`, so that the
+                // correct number of words is popped off the operand stack.
+                let value = category.map(|_| {
+                    let slot = self.pop_slot();
+                    mk_variable(stack(slot.id))
                 });
                 vec![Statement::Return(value)]
             }
-            Instruction::Synchronized(_) => unimplemented!(),
+            Instruction::Synchronized(SynchronizedOp::Enter) => {
+                let lock = self.pop();
+                // `reconstruct_try_catch` pairs this with the matching
+                // `monitorexit` (and the catch-all handler that releases the
+                // lock on the exceptional path) and, when the guarded region is
+                // a single basic block, folds it into `Statement::Synchronized`;
+                // a larger region is folded into a `Statement::Try` instead.
+                vec![Statement::MonitorEnter(mk_variable(stack(lock)))]
+            }
+            Instruction::Synchronized(SynchronizedOp::Exit) => {
+                let lock = self.pop();
+                vec![Statement::MonitorExit(mk_variable(stack(lock)))]
+            }
+        }
+    }
+
+    /// `invokedynamic` doesn't name a concrete method: it names a call site,
+    /// resolved through a `BootstrapMethods` entry. When the bootstrap is the
+    /// standard `LambdaMetafactory` one, the call site is really building a
+    /// lambda/method-reference closure, so it's reconstructed as
+    /// `Expr::Lambda` instead of a generic dynamic call.
+    fn execute_invoke_dynamic(&mut self, call_site_index: u16, metadata: &Metadata) -> Vec<Statement> {
+        let call_site = &metadata.invoke_dynamic_refs[&call_site_index];
+        let name_and_type = &metadata.name_refs[&call_site.name_and_type_index];
+        let signature = match name_and_type.typ {
+            Descriptor::Signature(ref signature) => signature.clone(),
+            Descriptor::Type(ref typ) => panic!("invokedynamic call site descriptor is not a method signature: {:?}", typ),
+        };
+        let mut captured_args = Vec::with_capacity(signature.parameters.len());
+        for _ in &signature.parameters {
+            let slot = self.pop_slot();
+            captured_args.push(mk_variable(stack(slot.id)));
+        }
+        captured_args.reverse();
+
+        let bootstrap = &metadata.bootstrap_methods[&call_site.bootstrap_method_attr_index];
+        let bootstrap_handle = &metadata.method_handle_refs[&bootstrap.method_ref];
+        let bootstrap_method = &metadata.method_refs[&bootstrap_handle.reference_index];
+        let bootstrap_class = &metadata.class_refs[&bootstrap_method.class_ref];
+
+        let return_type = signature.return_type.clone();
+        let expr = if bootstrap_class.0 == "java/lang/invoke/LambdaMetafactory" &&
+            (bootstrap_method.name == "metafactory" || bootstrap_method.name == "altMetafactory") {
+            // The metafactory/altMetafactory static arguments are
+            // `(samMethodType, implMethod, instantiatedMethodType)`; the
+            // implementation method handle is the second one.
+            let target_handle_index = bootstrap.arguments[1];
+            let target_handle = &metadata.method_handle_refs[&target_handle_index];
+            let target_method_ref = metadata.method_refs[&target_handle.reference_index].clone();
+            Expr::Lambda {
+                signature: signature,
+                target_method_ref: target_method_ref,
+                captured_args: captured_args,
+            }
+        } else {
+            Expr::DynamicCall {
+                name: name_and_type.name.clone(),
+                signature: signature,
+                args: captured_args,
+            }
+        };
+
+        // A call site always produces the functional-interface instance (or,
+        // for the generic fallback, whatever the descriptor's return type
+        // is); `void`-returning call sites don't occur in practice, but
+        // handle it the same way a regular `Invoke` does, for consistency.
+        if return_type == Type::Void {
+            vec![stmt_expr(expr)]
+        } else {
+            let result = self.push_cat(Category::of(&return_type));
+            vec![stmt_expr(Expr::Assign {
+                from: Box::new(expr),
+                op: None,
+                to: Box::new(Assignable::Variable(stack(result), 0)),
+            })]
+        }
+    }
+
+    fn execute_obj_manip(&mut self, op: &ObjManipOp, metadata: &Metadata) -> Vec<Statement> {
+        match *op {
+            ObjManipOp::New { class_ref } => {
+                let class = &metadata.class_refs[&class_ref];
+                let result = self.push();
+                vec![stmt_expr(Expr::Assign {
+                    to: Box::new(Assignable::Variable(stack(result), 0)),
+                    op: None,
+                    from: Box::new(Expr::New(class.clone())),
+                })]
+            }
+            ObjManipOp::ArrayLength => {
+                let array = self.pop();
+                let result = self.push();
+                vec![stmt_expr(Expr::Assign {
+                    to: Box::new(Assignable::Variable(stack(result), 0)),
+                    op: None,
+                    from: Box::new(Expr::ArrayLength(Box::new(mk_variable(stack(array))))),
+                })]
+            }
+            ObjManipOp::ArrayLoad(typ) => {
+                let index = self.pop();
+                let array = self.pop();
+                let result = self.push_cat(Category::of(&typ));
+                vec![stmt_expr(Expr::Assign {
+                    to: Box::new(Assignable::Variable(stack(result), 0)),
+                    op: None,
+                    from: Box::new(Expr::Assignable(Box::new(Assignable::ArrayElement {
+                        array: Box::new(mk_variable(stack(array))),
+                        index: Box::new(mk_variable(stack(index))),
+                    }))),
+                })]
+            }
+            ObjManipOp::ArrayStore(typ) => {
+                let value = self.pop_slot();
+                debug_assert_eq!(value.category, Category::of(&typ));
+                let index = self.pop();
+                let array = self.pop();
+                vec![stmt_expr(Expr::Assign {
+                    to: Box::new(Assignable::ArrayElement {
+                        array: Box::new(mk_variable(stack(array))),
+                        index: Box::new(mk_variable(stack(index))),
+                    }),
+                    op: None,
+                    from: Box::new(mk_variable(stack(value.id))),
+                })]
+            }
+            ObjManipOp::CheckCast(class_ref) => {
+                let class = &metadata.class_refs[&class_ref];
+                let object = self.pop();
+                let result = self.push();
+                vec![stmt_expr(Expr::Assign {
+                    to: Box::new(Assignable::Variable(stack(result), 0)),
+                    op: None,
+                    from: Box::new(Expr::CheckCast(
+                        class.clone(),
+                        Box::new(mk_variable(stack(object))),
+                    )),
+                })]
+            }
+            ObjManipOp::InstanceOf(class_ref) => {
+                let class = &metadata.class_refs[&class_ref];
+                let object = self.pop();
+                let result = self.push();
+                vec![stmt_expr(Expr::Assign {
+                    to: Box::new(Assignable::Variable(stack(result), 0)),
+                    op: None,
+                    from: Box::new(Expr::InstanceOf(
+                        class.clone(),
+                        Box::new(mk_variable(stack(object))),
+                    )),
+                })]
+            }
+        }
+    }
+
+    /// A `long`/`double` constant occupies two stack words, just like any
+    /// other category-2 value; a local reports the category last stored into
+    /// it, and `Category::One` if it hasn't been stored to yet.
+    fn category_of_rvalue(&self, expr: &RValue, metadata: &Metadata) -> Category {
+        match *expr {
+            RValue::Constant(Literal::Long(_)) | RValue::Constant(Literal::Double(_)) => {
+                Category::Two
+            }
+            RValue::ConstantRef { const_ref } => {
+                match metadata.literals[&const_ref] {
+                    Literal::Long(_) | Literal::Double(_) => Category::Two,
+                    _ => Category::One,
+                }
+            }
+            // Only tells us the category once the local has been stored to
+            // at least once in this method; a `long`/`double` parameter
+            // that's loaded before ever being reassigned still falls
+            // through to `Category::One`, since the JVM's `lload`/`dload`
+            // carry no type tag of their own and the method signature isn't
+            // threaded through this pass.
+            RValue::LValue(LValue::Local(index)) => self
+                .local_categories
+                .get(&index)
+                .cloned()
+                .unwrap_or(Category::One),
+            _ => Category::One,
         }
     }
 
@@ -211,7 +602,9 @@ impl StackLayout {
                 }
             }
         };
-        self.0 -= remove;
+        for _ in 0..remove {
+            self.pop();
+        }
         result
     }
 
@@ -255,7 +648,10 @@ pub fn stack_to_vars(
             Declaration::Method(ref mut method) => {
                 handle_parameters(method);
             }
-            Declaration::Constructor(..) => unreachable!("no constructors at this point"),
+            Declaration::Constructor { ref modifiers, ref mut signature, ref mut code } => {
+                prepend_this_and_params(modifiers, signature, code);
+            }
+            Declaration::StaticInitializer { .. } => {}
             Declaration::Field(..) => {}
         }
     }
@@ -269,11 +665,20 @@ fn transform(
     use petgraph::visit::Dfs;
     let mut stack_at_bb = vec![None; cfg.graph.node_count()];
     stack_at_bb[0] = Some(StackLayout::new());
+    // A handler block is entered by the JVM clearing the operand stack and
+    // pushing exactly the thrown exception, regardless of what the guarded
+    // block's stack looked like, so it's seeded rather than inferred from an
+    // incoming edge.
+    for handler in &cfg.exception_handlers {
+        let mut handler_stack = StackLayout::new();
+        handler_stack.push();
+        stack_at_bb[handler.handler_block.index()] = Some(handler_stack);
+    }
     let mut new_bbs = vec![BasicBlock::default(); cfg.graph.node_count()];
     let mut dfs = Dfs::new(&cfg.graph, NodeIndex::new(0));
     while let Some(v) = dfs.next(&cfg.graph) {
         let index = v.index();
-        let mut stack = stack_at_bb[index].unwrap();
+        let mut stack = stack_at_bb[index].clone().unwrap();
         new_bbs[index] = {
             let bb = &mut cfg.graph[v];
             let mut new_bb = BasicBlock::default();
@@ -285,36 +690,198 @@ fn transform(
         };
         for w in cfg.graph.neighbors_directed(v, Direction::Outgoing) {
             let stack_at_w = &mut stack_at_bb[w.index()];
-            if let Some(stack_at_w) = *stack_at_w {
-                // Assert that all paths to w result in the same stack size:
+            if let Some(ref canonical) = *stack_at_w {
+                // All paths into w must agree on the stack's word layout
+                // (count and category per slot). They don't need to agree on
+                // the concrete ids: w was reached by a different number of
+                // pushes along each predecessor, e.g. an `if` arm that does
+                // one extra push/pop before the merge, so `next_id` and the
+                // slot ids legitimately differ even though the shape is the
+                // same.
                 assert_eq!(
-                    stack,
-                    stack_at_w,
-                    "expected stack {:?} at beginning of node #{} but found {:?}",
-                    stack,
+                    stack.categories(),
+                    canonical.categories(),
+                    "expected stack shape {:?} at beginning of node #{} but found {:?}",
+                    stack.categories(),
                     w.index(),
-                    stack_at_w
+                    canonical.categories()
                 );
+                // w's own statements were generated against `canonical`'s ids,
+                // so bring this predecessor's values in line with those
+                // before falling through into it.
+                new_bbs[index]
+                    .stmts
+                    .append(&mut stack.bridge_to(canonical));
             } else {
-                *stack_at_w = Some(stack);
+                *stack_at_w = Some(stack.clone());
             }
         }
     }
     use std::mem;
-    Cfg {
+    let mut new_cfg = Cfg {
         graph: cfg.graph.map(
             |nx, _| mem::replace(&mut new_bbs[nx.index()], BasicBlock::default()),
             |_, e| *e,
         ),
         entry_point: cfg.entry_point,
         exit_point: cfg.exit_point,
+        exception_handlers: cfg.exception_handlers.clone(),
+    };
+    reconstruct_try_catch(&mut new_cfg, metadata);
+    new_cfg
+}
+
+/// Folds the basic blocks covered by each exception-table entry into a
+/// `Statement::Try`, resolving `catch_type` through `class_refs` (`None`
+/// meaning a `finally`/catch-all handler). Handlers that guard the same
+/// `[start_block, end_block)` range are collected into one `Try`'s `catches`
+/// list, mirroring how a single `try` can have several `catch` clauses.
+fn reconstruct_try_catch(cfg: &mut Cfg<Statement, Expr>, metadata: &Metadata) {
+    use std::collections::BTreeMap;
+    let mut regions: BTreeMap<(usize, usize), Vec<&ExceptionHandler>> = BTreeMap::new();
+    for handler in &cfg.exception_handlers {
+        regions
+            .entry((handler.start_block.index(), handler.end_block.index()))
+            .or_insert_with(Vec::new)
+            .push(handler);
+    }
+    // A properly-nested `try` inside another `try`'s region always has a
+    // strictly smaller span than the one enclosing it -- the JVM verifier
+    // rejects exception-table entries that partially overlap. Processing
+    // the smallest spans first means a nested region is already folded into
+    // a single `Statement::Try` node (occupying just its `start` block) by
+    // the time the enclosing region is extracted, instead of both regions
+    // draining -- and corrupting -- the same blocks.
+    let mut regions: Vec<_> = regions.into_iter().collect();
+    regions.sort_by_key(|&((start, end), _)| end - start);
+    for ((start, end), handlers) in regions {
+        // `synchronized (lock) { ... }` compiles to a `monitorenter` right
+        // before the guarded region, a `monitorexit` at its end, and a single
+        // catch-all handler that runs `monitorexit; athrow` to release the
+        // lock on the exceptional path. That's indistinguishable from an
+        // ordinary `try`/`finally` by the exception table alone, so recognize
+        // the shape here instead of surfacing it as a `finally` that rethrows.
+        // Only the common single-block case is recognized: a body with
+        // control flow still gets folded below, via `extract_region`, just
+        // as an ordinary `Try`/`finally` rather than `Statement::Synchronized`.
+        if end - start == 1 {
+            if let Some(lock) = synchronized_lock(cfg, start, &handlers) {
+                let mut body = cfg.graph[NodeIndex::new(start)].stmts.drain(..).collect::<Vec<_>>();
+                body.remove(0);
+                body.pop();
+                cfg.graph[NodeIndex::new(start)].stmts = vec![Statement::Synchronized {
+                    lock: lock,
+                    body: body,
+                }];
+                continue;
+            }
+        }
+        let body = extract_region(cfg, start, end);
+        let mut catches = vec![];
+        let mut finally = None;
+        for handler in handlers {
+            let handler_body = cfg.graph[handler.handler_block].stmts.drain(..).collect::<Vec<_>>();
+            match handler.catch_type {
+                Some(type_index) => {
+                    let class = metadata.class_refs[&type_index].clone();
+                    catches.push((class, handler_body));
+                }
+                None => finally = Some(handler_body),
+            }
+        }
+        cfg.graph[NodeIndex::new(start)].stmts = vec![Statement::Try {
+            body: body,
+            catches: catches,
+            finally: finally,
+        }];
+    }
+}
+
+/// Pulls the blocks `[start, end)` out of `cfg` into their own `Cfg`,
+/// preserving the internal edges (and terminators) between them instead of
+/// concatenating their statements into one flat list -- flattening silently
+/// dropped any branch or loop inside a `try` body. Blocks outside the range
+/// are blanked rather than removed (removing would renumber every surviving
+/// `NodeIndex`, and nothing else in this pass tolerates that), so an edge
+/// leaving the region early -- a `return`, or a `break` out of an enclosing
+/// loop -- lands on an empty sink block in the extracted `Cfg` instead of
+/// vanishing. `cfg` itself is left with `[start, end)` blanked out, same as
+/// the flat-`Vec` version left them drained.
+fn extract_region(cfg: &mut Cfg<Statement, Expr>, start: usize, end: usize) -> Cfg<Statement, Expr> {
+    use std::mem;
+    let node_count = cfg.graph.node_count();
+    let mut extracted: Vec<Option<BasicBlock>> = (0..node_count)
+        .map(|i| if i >= start && i < end {
+            Some(mem::replace(&mut cfg.graph[NodeIndex::new(i)], BasicBlock::default()))
+        } else {
+            None
+        })
+        .collect();
+    let sub_graph = cfg.graph.map(
+        |nx, _| extracted[nx.index()].take().unwrap_or_default(),
+        |_, e| *e,
+    );
+    Cfg {
+        graph: sub_graph,
+        entry_point: NodeIndex::new(start),
+        exit_point: NodeIndex::new(end - 1),
+        exception_handlers: vec![],
+    }
+}
+
+/// Checks whether the single block `start` and `handlers` match the
+/// `monitorenter`/`monitorexit` shape javac emits for `synchronized` blocks
+/// (see `reconstruct_try_catch`) and, if so, drains the handler block and
+/// returns the lock expression. Relies on the JVM guarantee that a compiler
+/// always reuses the same local for a lock's `monitorenter` and every
+/// corresponding `monitorexit`, rather than re-deriving that identity by
+/// comparing the two expressions.
+fn synchronized_lock(
+    cfg: &mut Cfg<Statement, Expr>,
+    start: usize,
+    handlers: &[&ExceptionHandler],
+) -> Option<Expr> {
+    if handlers.len() != 1 || handlers[0].catch_type.is_some() {
+        return None;
+    }
+    let body = &cfg.graph[NodeIndex::new(start)].stmts;
+    match (body.first(), body.last()) {
+        (Some(&Statement::MonitorEnter(_)), Some(&Statement::MonitorExit(_))) => {}
+        _ => return None,
+    }
+    let handler = handlers[0];
+    let handler_body: Vec<_> = cfg.graph[handler.handler_block].stmts.drain(..).collect();
+    match handler_body[..] {
+        [Statement::MonitorExit(_), Statement::Throw(_)] => {}
+        _ => {
+            // Not the synchronized-block shape after all (an ordinary
+            // catch-all `finally` can also start with a lone statement);
+            // put it back so the normal `Try`/`finally` path can use it.
+            cfg.graph[handler.handler_block].stmts = handler_body;
+            return None;
+        }
+    }
+    match cfg.graph[NodeIndex::new(start)].stmts.first() {
+        Some(&Statement::MonitorEnter(ref lock)) => Some(lock.clone()),
+        _ => unreachable!(),
     }
 }
 
 fn handle_parameters(method: &mut Method<Cfg<Statement, Expr>>) {
+    prepend_this_and_params(&method.modifiers, &mut method.signature, &mut method.code);
+}
+
+/// Assigns `local_0` from `this` (unless the member is static) and names the
+/// remaining locals after the declared parameters. Shared between methods
+/// and constructors, which both bind `this`/parameters the same way.
+fn prepend_this_and_params(
+    modifiers: &[Modifier],
+    signature: &mut Signature,
+    code: &mut Option<Cfg<Statement, Expr>>,
+) {
     let mut local_index = 0;
     let mut assignments = vec![];
-    if !method.modifiers.contains(&Modifier::Static) {
+    if !modifiers.contains(&Modifier::Static) {
         let to = Box::new(Assignable::Variable(local(local_index), 0));
         let from = Box::new(Expr::This);
         assignments.push(Statement::Expr(Expr::Assign {
@@ -324,12 +891,85 @@ fn handle_parameters(method: &mut Method<Cfg<Statement, Expr>>) {
         }));
         local_index += 1;
     }
-    for parameter in method.signature.parameters.iter_mut() {
+    for parameter in signature.parameters.iter_mut() {
         parameter.0 = local(local_index);
         local_index += 1;
     }
-    if let Some(ref mut cfg) = method.code {
+    if let Some(ref mut cfg) = *code {
         let entry_block = &mut cfg.graph.node_weight_mut(cfg.entry_point).unwrap();
         entry_block.stmts.append(&mut assignments);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pins `StackLayout::get`'s indexing convention: `i == 1` is the
+    // top-most word, matching the 1-based indices `LValue::Stack` and
+    // `LValue::InstanceField::object_stack_index` are built with.
+    #[test]
+    fn get_is_one_based_from_the_top() {
+        let mut stack = StackLayout::new();
+        let bottom = stack.push();
+        let top = stack.push();
+        assert_eq!(stack.get(1), top);
+        assert_eq!(stack.get(2), bottom);
+    }
+
+    // A category-2 slot occupies two words, so it shifts the 1-based offset
+    // of anything beneath it by one relative to a category-1 slot.
+    #[test]
+    fn get_accounts_for_category_two_word_width() {
+        let mut stack = StackLayout::new();
+        let bottom = stack.push();
+        let wide = stack.push_cat(Category::Two);
+        assert_eq!(stack.get(1), wide);
+        assert_eq!(stack.get(2), wide);
+        assert_eq!(stack.get(3), bottom);
+    }
+
+    // Two predecessors of a join can mint a different number of fresh ids
+    // along the way (e.g. one arm did an extra push/pop) and still agree on
+    // the stack's word shape -- that's the only thing a join should require.
+    #[test]
+    fn categories_match_across_differently_numbered_layouts() {
+        let mut a = StackLayout::new();
+        a.push();
+        a.push_cat(Category::Two);
+
+        let mut b = StackLayout::new();
+        b.push();
+        b.push();
+        b.pop();
+        b.push_cat(Category::Two);
+
+        assert_eq!(a.categories(), b.categories());
+    }
+
+    // `bridge_to` reconciles a predecessor's slot ids with whichever
+    // predecessor's layout was recorded first for the join block, so code
+    // generated against the canonical ids keeps working regardless of which
+    // predecessor actually ran.
+    #[test]
+    fn bridge_to_copies_mismatched_ids_only() {
+        let mut canonical = StackLayout::new();
+        let shared = canonical.push();
+        let canonical_top = canonical.push_cat(Category::Two);
+
+        let mut other = StackLayout::new();
+        other.push();
+        other.push();
+        other.pop();
+        let other_top = other.push_cat(Category::Two);
+        assert_eq!(other.get(3), shared);
+
+        let bridge = other.bridge_to(&canonical);
+        let expected = vec![stmt_expr(Expr::Assign {
+            to: Box::new(Assignable::Variable(stack(canonical_top), 0)),
+            op: None,
+            from: Box::new(mk_variable(stack(other_top))),
+        })];
+        assert_eq!(format!("{:?}", bridge), format!("{:?}", expected));
+    }
+}
@@ -20,9 +20,32 @@ pub fn transform(class_file: ClassFile) -> CompilationUnit {
         field_refs: HashMap::new(),
         method_refs: HashMap::new(),
         name_refs: HashMap::new(),
+        method_handle_refs: HashMap::new(),
+        method_type_refs: HashMap::new(),
+        invoke_dynamic_refs: HashMap::new(),
+        bootstrap_methods: HashMap::new(),
+        this_class: ClassRef(String::new()),
+        super_class: None,
+        interfaces: vec![],
     };
     unit.modifiers = class_flags_to_modifiers(&class_file.access_flags);
     process_constant_pool(&mut unit, class_file.constant_pool);
+    // `this_class`/`super_class`/`interfaces` are indices into the constant
+    // pool just parsed above, so resolve them through `class_refs` rather
+    // than re-deriving the class names by hand.
+    unit.this_class = unit.class_refs[&class_file.this_class].clone();
+    unit.super_class = if class_file.super_class == 0 {
+        None
+    } else {
+        Some(unit.class_refs[&class_file.super_class].clone())
+    };
+    unit.interfaces = class_file
+        .interfaces
+        .iter()
+        .map(|index| unit.class_refs[index].clone())
+        .collect();
+    process_bootstrap_methods(&mut unit, &class_file.attributes);
+    process_fields(&mut unit, &class_file.fields);
     process_methods(&mut unit, &class_file.methods);
     unit
 }
@@ -50,9 +73,29 @@ fn class_flags_to_modifiers(flags: &AccessFlags) -> Vec<Modifier> {
     modifiers
 }
 
+fn method_ref_from(constant_pool: &ConstantPool, class_index: u16, name_index: u16) -> MethodRef {
+    let (name_index, descriptor_index) = match *constant_pool.lookup(name_index) {
+        ConstantInfo::NameAndType { name_index, descriptor_index } => {
+            (name_index, descriptor_index)
+        }
+        ref c => panic!("Index doesn't point to a NameAndType but to: {:#?}", c),
+    };
+    let name = constant_pool.lookup_string(name_index).to_owned();
+    let descriptor = constant_pool.lookup_string(descriptor_index);
+    MethodRef {
+        class_ref: class_index,
+        name: name,
+        signature: descriptor_to_signature(descriptor),
+    }
+}
+
 fn process_constant_pool(unit: &mut CompilationUnit, constant_pool: ConstantPool) {
-    for (index, constant) in constant_pool.constants.iter().enumerate() {
-        let index = index as u16 + 1; // plus one because of weird indexing in the JVM spec
+    // Can't use `.enumerate()` here: `Long`/`Double` entries take up two
+    // constant-pool indices (the second is left unused), so the running
+    // index has to be tracked by hand instead of derived from the `Vec`
+    // position.
+    let mut index: u16 = 1; // plus one because of weird indexing in the JVM spec
+    for constant in &constant_pool.constants {
         match *constant {
             ConstantInfo::Utf8(ref str) => {
                 unit.string_constants.insert(index, str.to_owned());
@@ -60,6 +103,17 @@ fn process_constant_pool(unit: &mut CompilationUnit, constant_pool: ConstantPool
             ConstantInfo::Integer(int) => {
                 unit.java_constants.insert(index, JavaConstant::Integer(int));
             }
+            ConstantInfo::Long(long) => {
+                unit.java_constants.insert(index, JavaConstant::Long(long));
+                index += 1; // occupies the following index as well
+            }
+            ConstantInfo::Float(float) => {
+                unit.java_constants.insert(index, JavaConstant::Float(float));
+            }
+            ConstantInfo::Double(double) => {
+                unit.java_constants.insert(index, JavaConstant::Double(double));
+                index += 1; // occupies the following index as well
+            }
             ConstantInfo::Class { name_index } => {
                 let name = constant_pool.lookup_string(name_index);
                 unit.class_refs.insert(index, ClassRef(name.to_owned()));
@@ -86,21 +140,29 @@ fn process_constant_pool(unit: &mut CompilationUnit, constant_pool: ConstantPool
                                        });
             }
             ConstantInfo::MethodRef { class_index, name_index } => {
-                let (name_index, descriptor_index) = match *constant_pool.lookup(name_index) {
-                    ConstantInfo::NameAndType { name_index, descriptor_index } => {
-                        (name_index, descriptor_index)
-                    }
-                    ref c => panic!("Index doesn't point to a NameAndType but to: {:#?}", c),
-                };
-                let name = constant_pool.lookup_string(name_index).to_owned();
+                unit.method_refs.insert(index, method_ref_from(&constant_pool, class_index, name_index));
+            }
+            ConstantInfo::InterfaceMethodref { class_index, name_index } => {
+                unit.method_refs.insert(index, method_ref_from(&constant_pool, class_index, name_index));
+            }
+            ConstantInfo::MethodHandle { reference_kind, reference_index } => {
+                unit.method_handle_refs.insert(index,
+                                               MethodHandleRef {
+                                                   reference_kind: reference_kind,
+                                                   reference_index: reference_index,
+                                               });
+            }
+            ConstantInfo::MethodType { descriptor_index } => {
                 let descriptor = constant_pool.lookup_string(descriptor_index);
-                let signature = descriptor_to_signature(descriptor);
-                unit.method_refs.insert(index,
-                                        MethodRef {
-                                            class_ref: class_index,
-                                            name: name,
-                                            signature: signature,
-                                        });
+                unit.method_type_refs.insert(index, descriptor_to_signature(descriptor));
+            }
+            ConstantInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index } |
+            ConstantInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                unit.invoke_dynamic_refs.insert(index,
+                                                InvokeDynamicRef {
+                                                    bootstrap_method_attr_index: bootstrap_method_attr_index,
+                                                    name_and_type_index: name_and_type_index,
+                                                });
             }
             ConstantInfo::NameAndType { name_index, descriptor_index } => {
                 let name = constant_pool.lookup_string(name_index).to_owned();
@@ -117,7 +179,100 @@ fn process_constant_pool(unit: &mut CompilationUnit, constant_pool: ConstantPool
                                       });
             }
         }
+        index += 1;
+    }
+}
+
+/// Parses the class-level `BootstrapMethods` attribute (JVMS 4.7.23), which
+/// backs every `invokedynamic`/`Dynamic` constant-pool entry but isn't a
+/// constant-pool entry itself.
+fn process_bootstrap_methods(unit: &mut CompilationUnit, attributes: &[AttributeInfo]) {
+    for attribute in attributes {
+        let name = unit.lookup_string(attribute.name_index);
+        if name == "BootstrapMethods" {
+            for (index, bootstrap_method) in parse_bootstrap_methods_attribute(&attribute.info)
+                .into_iter()
+                .enumerate()
+            {
+                unit.bootstrap_methods.insert(index as u16, bootstrap_method);
+            }
+            break;
+        }
+    }
+}
+
+fn parse_bootstrap_methods_attribute(info: &[u8]) -> Vec<BootstrapMethod> {
+    let read_u16 = |pos: usize| (info[pos] as u16) << 8 | info[pos + 1] as u16;
+    let num_bootstrap_methods = read_u16(0);
+    let mut methods = Vec::with_capacity(num_bootstrap_methods as usize);
+    let mut pos = 2;
+    for _ in 0..num_bootstrap_methods {
+        let method_ref = read_u16(pos);
+        let num_arguments = read_u16(pos + 2);
+        pos += 4;
+        let mut arguments = Vec::with_capacity(num_arguments as usize);
+        for _ in 0..num_arguments {
+            arguments.push(read_u16(pos));
+            pos += 2;
+        }
+        methods.push(BootstrapMethod {
+            method_ref: method_ref,
+            arguments: arguments,
+        });
+    }
+    methods
+}
+
+fn process_fields(unit: &mut CompilationUnit, fields: &[FieldInfo]) {
+    for field in fields {
+        let transformed = transform_field(&unit, field);
+        unit.declarations.push(transformed);
+    }
+}
+
+fn transform_field(unit: &CompilationUnit, field: &FieldInfo) -> Declaration {
+    let mut constant_value = None;
+    for attribute in &field.attributes {
+        let name = unit.lookup_string(attribute.name_index);
+        if name == "ConstantValue" {
+            let index = (attribute.info[0] as u16) << 8 | attribute.info[1] as u16;
+            constant_value = unit.java_constants.get(&index).cloned();
+            break;
+        }
+    }
+    Declaration::Field {
+        modifiers: field_flags_to_modifiers(&field.access_flags),
+        name: unit.lookup_string(field.name_index).to_owned(),
+        typ: descriptor_to_type(&mut unit.lookup_string(field.descriptor_index).chars()),
+        constant_value: constant_value,
+    }
+}
+
+fn field_flags_to_modifiers(flags: &AccessFlags) -> Vec<Modifier> {
+    let mut modifiers = vec![];
+    if flags.contains(ACC_PUBLIC) {
+        modifiers.push(Modifier::Public);
+    }
+    if flags.contains(ACC_PROTECTED) {
+        modifiers.push(Modifier::Protected);
+    }
+    if flags.contains(ACC_PRIVATE) {
+        modifiers.push(Modifier::Private);
+    }
+    if flags.contains(ACC_STATIC) {
+        modifiers.push(Modifier::Static);
     }
+    if flags.contains(ACC_FINAL) {
+        modifiers.push(Modifier::Final);
+    }
+    // Field specific flags:
+    if flags.contains(ACC_VOLATILE) {
+        modifiers.push(Modifier::Volatile);
+    }
+    if flags.contains(ACC_TRANSIENT) {
+        modifiers.push(Modifier::Transient);
+    }
+    modifiers
 }
 
 fn process_methods(unit: &mut CompilationUnit, methods: &[MethodInfo]) {
@@ -129,20 +284,42 @@ fn process_methods(unit: &mut CompilationUnit, methods: &[MethodInfo]) {
 
 fn transform_method(unit: &CompilationUnit, method: &MethodInfo) -> Declaration {
     let mut code = None;
+    let mut generic_signature = None;
     for attribute in &method.attributes {
         let name = unit.lookup_string(attribute.name_index);
         if name == "Code" {
             let code_attribute = parse_code_attribute(&attribute.info).unwrap();
             let disassembly = disassemble(unit, code_attribute);
             code = Some(disassembly);
-            break;
+        } else if name == "Signature" {
+            let index = (attribute.info[0] as u16) << 8 | attribute.info[1] as u16;
+            generic_signature = Some(unit.lookup_string(index));
         }
     }
-    Declaration::Method {
-        modifiers: method_flags_to_modifiers(&method.access_flags),
-        name: unit.lookup_string(method.name_index).to_owned(),
-        signature: descriptor_to_signature(unit.lookup_string(method.descriptor_index)),
-        code: code,
+    // Prefer the generic `Signature` attribute, when present, over the
+    // erased descriptor: it's the only place `List<String>`, type
+    // variables, and bounds survive compilation.
+    let signature = match generic_signature {
+        Some(signature) => parse_method_signature(signature),
+        None => descriptor_to_signature(unit.lookup_string(method.descriptor_index)),
+    };
+    let name = unit.lookup_string(method.name_index);
+    let modifiers = method_flags_to_modifiers(&method.access_flags);
+    if name == "<init>" {
+        Declaration::Constructor {
+            modifiers: modifiers,
+            signature: signature,
+            code: code,
+        }
+    } else if name == "<clinit>" {
+        Declaration::StaticInitializer { code: code }
+    } else {
+        Declaration::Method {
+            modifiers: modifiers,
+            name: name.to_owned(),
+            signature: signature,
+            code: code,
+        }
     }
 }
 
@@ -203,6 +380,7 @@ fn descriptor_to_signature(descriptor: &str) -> Signature {
     Signature {
         parameters: params,
         return_type: return_type,
+        type_parameters: vec![],
     }
 }
 
@@ -232,3 +410,196 @@ fn descriptor_to_type<I: Iterator<Item = char>>(chars: &mut I) -> Type {
         _ => panic!("Invalid start of type descriptor: {:?}", next),
     }
 }
+
+/// Inverse of `descriptor_to_signature`, used by the assembler to turn a
+/// `Signature` back into a method descriptor string.
+pub(crate) fn signature_to_descriptor(signature: &Signature) -> String {
+    let mut descriptor = String::from("(");
+    for parameter in &signature.parameters {
+        descriptor.push_str(&type_to_descriptor(parameter, &signature.type_parameters));
+    }
+    descriptor.push(')');
+    descriptor.push_str(&type_to_descriptor(&signature.return_type, &signature.type_parameters));
+    descriptor
+}
+
+/// A type variable erases to its declared class bound, or its first
+/// declared interface bound if there's no class bound, or `Object` if it's
+/// unbounded (JVMS 4.7.9.1) -- never unconditionally to `Object`, since a
+/// bounded parameter like `<T extends Number> void f(T)` really erases to
+/// `(Ljava/lang/Number;)V`, not `(Ljava/lang/Object;)V`.
+fn erased_type_var_bound<'a>(name: &str, type_parameters: &'a [TypeParameter]) -> Option<&'a Type> {
+    let parameter = type_parameters.iter().find(|param| param.name == name)?;
+    parameter
+        .class_bound
+        .as_ref()
+        .or_else(|| parameter.interface_bounds.first())
+}
+
+/// Inverse of `descriptor_to_type`, used by the assembler to turn a `Type`
+/// back into a (possibly array/reference) type descriptor string.
+/// `type_parameters` resolves any `Type::TypeVar` to its erasure; pass the
+/// enclosing method/constructor signature's `type_parameters` where one is
+/// in scope, or `&[]` where it isn't (e.g. a field's declared type, whose
+/// type variables would need the class's own signature to resolve and
+/// fall back to `Object` like an unbounded one).
+pub(crate) fn type_to_descriptor(typ: &Type, type_parameters: &[TypeParameter]) -> String {
+    match *typ {
+        Type::Byte => "B".to_owned(),
+        Type::Char => "C".to_owned(),
+        Type::Double => "D".to_owned(),
+        Type::Float => "F".to_owned(),
+        Type::Int => "I".to_owned(),
+        Type::Long => "J".to_owned(),
+        Type::Reference(ref class_name) => format!("L{};", class_name),
+        Type::Short => "S".to_owned(),
+        Type::Void => "V".to_owned(),
+        Type::Boolean => "Z".to_owned(),
+        Type::Array(ref element) => format!("[{}", type_to_descriptor(element, type_parameters)),
+        // Generic types carry no runtime representation: a descriptor only
+        // ever sees their erasure.
+        Type::Parameterized(ref raw, _) => type_to_descriptor(raw, type_parameters),
+        Type::TypeVar(ref name) => match erased_type_var_bound(name, type_parameters) {
+            Some(bound) => type_to_descriptor(bound, type_parameters),
+            None => "Ljava/lang/Object;".to_owned(),
+        },
+        Type::Wildcard(Wildcard::Extends(ref bound)) => type_to_descriptor(bound, type_parameters),
+        Type::Wildcard(Wildcard::Unbounded) |
+        Type::Wildcard(Wildcard::Super(_)) => "Ljava/lang/Object;".to_owned(),
+    }
+}
+
+/// Parses a method's generic `Signature` attribute (JVMS 4.7.9.1), which is
+/// strictly richer than the erased descriptor: formal type parameters and
+/// their bounds, parameterized types (`List<String>`), type variables, and
+/// wildcards. Throws-signatures are parsed (to keep the cursor in sync) but
+/// not kept, since `Signature` has no slot for them yet.
+fn parse_method_signature(signature: &str) -> Signature {
+    let mut chars = signature.chars().peekable();
+    let type_parameters = parse_formal_type_parameters(&mut chars);
+    let next = chars.next().unwrap();
+    if next != '(' {
+        panic!("Expected open paren at beginning of method signature: {:?}",
+               signature);
+    }
+    let mut parameters = vec![];
+    while *chars.peek().unwrap() != ')' {
+        parameters.push(parse_type_signature(&mut chars));
+    }
+    chars.next(); // ')'
+    let return_type = parse_type_signature(&mut chars);
+    while chars.peek() == Some(&'^') {
+        chars.next();
+        parse_type_signature(&mut chars);
+    }
+    Signature {
+        parameters: parameters,
+        return_type: return_type,
+        type_parameters: type_parameters,
+    }
+}
+
+fn parse_formal_type_parameters<I>(chars: &mut ::std::iter::Peekable<I>) -> Vec<TypeParameter>
+    where I: Iterator<Item = char>
+{
+    if chars.peek() != Some(&'<') {
+        return vec![];
+    }
+    chars.next(); // '<'
+    let mut type_parameters = vec![];
+    while *chars.peek().unwrap() != '>' {
+        let mut name = String::new();
+        while *chars.peek().unwrap() != ':' {
+            name.push(chars.next().unwrap());
+        }
+        chars.next(); // ':'
+        // An absent class bound looks like `T::Linterface;` (two colons back
+        // to back); only an interface-bound list follows in that case.
+        let class_bound = if *chars.peek().unwrap() == ':' {
+            None
+        } else {
+            Some(parse_type_signature(chars))
+        };
+        let mut interface_bounds = vec![];
+        while *chars.peek().unwrap() == ':' {
+            chars.next();
+            interface_bounds.push(parse_type_signature(chars));
+        }
+        type_parameters.push(TypeParameter {
+            name: name,
+            class_bound: class_bound,
+            interface_bounds: interface_bounds,
+        });
+    }
+    chars.next(); // '>'
+    type_parameters
+}
+
+fn parse_type_signature<I>(chars: &mut ::std::iter::Peekable<I>) -> Type
+    where I: Iterator<Item = char>
+{
+    match *chars.peek().unwrap() {
+        'T' => {
+            chars.next();
+            let mut name = String::new();
+            for ch in chars {
+                if ch == ';' {
+                    break;
+                }
+                name.push(ch);
+            }
+            Type::TypeVar(name)
+        }
+        'L' => parse_class_type_signature(chars),
+        '[' => {
+            chars.next();
+            Type::Array(Box::new(parse_type_signature(chars)))
+        }
+        // Base types and `V` share the descriptor grammar exactly.
+        _ => descriptor_to_type(chars),
+    }
+}
+
+fn parse_class_type_signature<I>(chars: &mut ::std::iter::Peekable<I>) -> Type
+    where I: Iterator<Item = char>
+{
+    chars.next(); // 'L'
+    let mut class_name = String::new();
+    while *chars.peek().unwrap() != '<' && *chars.peek().unwrap() != ';' {
+        class_name.push(chars.next().unwrap());
+    }
+    let base = Type::Reference(class_name);
+    let result = if *chars.peek().unwrap() == '<' {
+        chars.next(); // '<'
+        let mut arguments = vec![];
+        while *chars.peek().unwrap() != '>' {
+            arguments.push(parse_type_argument(chars));
+        }
+        chars.next(); // '>'
+        Type::Parameterized(Box::new(base), arguments)
+    } else {
+        base
+    };
+    chars.next(); // ';' (or the inner-class suffix, not modeled yet)
+    result
+}
+
+fn parse_type_argument<I>(chars: &mut ::std::iter::Peekable<I>) -> Type
+    where I: Iterator<Item = char>
+{
+    match *chars.peek().unwrap() {
+        '*' => {
+            chars.next();
+            Type::Wildcard(Wildcard::Unbounded)
+        }
+        '+' => {
+            chars.next();
+            Type::Wildcard(Wildcard::Extends(Box::new(parse_type_signature(chars))))
+        }
+        '-' => {
+            chars.next();
+            Type::Wildcard(Wildcard::Super(Box::new(parse_type_signature(chars))))
+        }
+        _ => parse_type_signature(chars),
+    }
+}